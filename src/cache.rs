@@ -0,0 +1,138 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAX_DEPTH: usize = 6;
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+
+// cached at ~/.cache/repox/index-<hash of scan_root>
+pub fn list_repos(scan_root: &Path, home: &str, refresh: bool) -> io::Result<Vec<PathBuf>> {
+    let cache_file = cache_path(home, scan_root);
+
+    if !refresh {
+        if let Some(cached) = read_cache(&cache_file) {
+            return Ok(cached);
+        }
+    }
+
+    let mut repos = Vec::new();
+    walk(scan_root, 0, &mut repos);
+    repos.sort();
+
+    write_cache(&cache_file, &repos)?;
+
+    Ok(repos)
+}
+
+fn cache_path(home: &str, scan_root: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    scan_root.hash(&mut hasher);
+
+    PathBuf::from(home)
+        .join(".cache")
+        .join("repox")
+        .join(format!("index-{:x}", hasher.finish()))
+}
+
+fn read_cache(cache_file: &Path) -> Option<Vec<PathBuf>> {
+    let metadata = fs::metadata(cache_file).ok()?;
+    let age = metadata.modified().ok()?.elapsed().ok()?;
+    if age > CACHE_TTL {
+        return None;
+    }
+
+    let content = fs::read_to_string(cache_file).ok()?;
+    Some(content.lines().map(PathBuf::from).collect())
+}
+
+fn write_cache(cache_file: &Path, repos: &[PathBuf]) -> io::Result<()> {
+    if let Some(parent) = cache_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let content = repos
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    fs::write(cache_file, content)
+}
+
+// bounded by MAX_DEPTH; doesn't descend into a clone's own .git internals
+fn walk(dir: &Path, depth: usize, repos: &mut Vec<PathBuf>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        if path.join(".git").exists() {
+            repos.push(path);
+            continue;
+        }
+
+        walk(&path, depth + 1, repos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("repox-cache-test-{}-{}-{}", std::process::id(), id, name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn walk_finds_clones_and_skips_git_internals() {
+        let root = temp_dir("walk");
+        fs::create_dir_all(root.join("a/.git/objects")).unwrap();
+        fs::create_dir_all(root.join("b/nested/.git")).unwrap();
+
+        let mut repos = Vec::new();
+        walk(&root, 0, &mut repos);
+        repos.sort();
+
+        assert_eq!(repos, vec![root.join("a"), root.join("b/nested")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn list_repos_caches_per_scan_root() {
+        let home = temp_dir("home");
+        let root_a = temp_dir("root_a");
+        let root_b = temp_dir("root_b");
+        fs::create_dir_all(root_a.join("a1/.git")).unwrap();
+        fs::create_dir_all(root_b.join("b1/.git")).unwrap();
+
+        let repos_a = list_repos(&root_a, home.to_str().unwrap(), false).unwrap();
+        let repos_b = list_repos(&root_b, home.to_str().unwrap(), false).unwrap();
+
+        assert_eq!(repos_a, vec![root_a.join("a1")]);
+        assert_eq!(repos_b, vec![root_b.join("b1")]);
+
+        fs::remove_dir_all(&home).unwrap();
+        fs::remove_dir_all(&root_a).unwrap();
+        fs::remove_dir_all(&root_b).unwrap();
+    }
+}
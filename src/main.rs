@@ -1,19 +1,41 @@
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::process::{Command, exit};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+mod cache;
 
 static PRINT_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
 
+// name/branch/remote/dir are only ever set by the TOML manifest format.
+#[derive(Clone)]
+struct RepoEntry {
+    group: String,
+    url: String,
+    name: Option<String>,
+    branch: Option<String>,
+    remote: Option<String>,
+    dir: Option<String>,
+}
+
 struct Config {
     cmd: String,
     sub_dir: String,
     parallels: usize,
     repox_file: PathBuf,
     dev_dir: PathBuf,
+    // tokens after a `--` separator, appended verbatim to the git invocation
+    // of any command other than status, clone and pull (those ignore them)
+    passthrough_args: Vec<String>,
+    // `-v` count; 2+ (`-vv`) turns on per-repo lifecycle logging
+    verbosity: u8,
+    quiet: bool,
 }
 
 impl Clone for Config {
@@ -24,6 +46,9 @@ impl Clone for Config {
             parallels: self.parallels,
             repox_file: self.repox_file.clone(),
             dev_dir: self.dev_dir.clone(),
+            passthrough_args: self.passthrough_args.clone(),
+            verbosity: self.verbosity,
+            quiet: self.quiet,
         }
     }
 }
@@ -34,31 +59,202 @@ fn print_sync<F: FnOnce()>(f: F) {
     f();
 }
 
-fn read_repos(repox_file: &Path) -> io::Result<Vec<String>> {
+// seconds since the Unix epoch; no date/time dependency here to format it further
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// -vv lifecycle line: dispatch, git command line, elapsed duration
+fn vlog(config: &Config, repo: &str, msg: &str) {
+    if config.verbosity < 2 {
+        return;
+    }
+    print_sync(|| {
+        eprintln!("[{}] {} {}", timestamp(), repo, msg);
+    });
+}
+
+// default-level per-repo error; suppressed by -q
+fn log_error(config: &Config, repo: &str, cmd: &str, err: impl fmt::Display) {
+    if config.quiet {
+        return;
+    }
+    print_sync(|| {
+        eprintln!("\n\x1B[31m[ERROR] {} failed on {}: {}\x1B[0m", cmd, repo, err);
+    });
+}
+
+// default-level per-repo header + output; suppressed by -q and when there's nothing to show
+fn log_result(config: &Config, repo: &str, cmd: &str, stdout: &str, stderr: &str) {
+    if config.quiet || (stdout.trim().is_empty() && stderr.trim().is_empty()) {
+        return;
+    }
+    print_sync(|| {
+        println!("\n\x1B[34m=== {}: {} ===\x1B[0m", cmd.to_uppercase(), repo);
+        if !stdout.trim().is_empty() {
+            println!("{}", stdout.trim());
+        }
+        if !stderr.trim().is_empty() {
+            eprintln!("{}", stderr.trim());
+        }
+    });
+}
+
+// Groups: inline `work::url`, or a `[work]` header applying to the bare
+// URLs that follow it until the next header.
+fn read_repos(repox_file: &Path) -> io::Result<Vec<RepoEntry>> {
     let file = File::open(repox_file)?;
     let reader = BufReader::new(file);
-    Ok(reader
-        .lines()
-        .filter_map(Result::ok)
-        .map(|line| line.trim().to_string())
-        .filter(|line| !line.is_empty() && !line.starts_with('#'))
-        .collect())
-}
-
-fn process_repo(config: &Config, repo: &str) {
-    let repo_name = repo
-        .trim_end_matches(".git")
-        .rsplit('/')
-        .next()
-        .unwrap_or(repo);
-    let local_repo = config.dev_dir.join(repo_name);
+
+    let mut repos = Vec::new();
+    let mut current_group = String::new();
+
+    for line in reader.lines().filter_map(Result::ok) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            current_group = line[1..line.len() - 1].trim().to_string();
+            continue;
+        }
+
+        let (group, url) = match line.split_once("::") {
+            Some((group, url)) => (group.trim().to_string(), url.trim().to_string()),
+            None => (current_group.clone(), line.to_string()),
+        };
+
+        repos.push(RepoEntry {
+            group,
+            url,
+            name: None,
+            branch: None,
+            remote: None,
+            dir: None,
+        });
+    }
+
+    Ok(repos)
+}
+
+// `-c path/to/file.toml`: a minimal `[[repo]]` table reader covering the
+// subset of TOML repox needs (flat string keys, no nesting/arrays).
+fn read_repos_toml(repox_file: &Path) -> io::Result<Vec<RepoEntry>> {
+    let content = fs::read_to_string(repox_file)?;
+
+    let mut repos = Vec::new();
+    let mut current: Option<HashMap<String, String>> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line == "[[repo]]" {
+            if let Some(fields) = current.take() {
+                repos.push(repo_entry_from_toml_fields(fields));
+            }
+            current = Some(HashMap::new());
+            continue;
+        }
+
+        if let (Some(fields), Some((key, value))) = (current.as_mut(), line.split_once('=')) {
+            let key = key.trim().to_string();
+            let value = value.trim().trim_matches('"').to_string();
+            fields.insert(key, value);
+        }
+    }
+
+    if let Some(fields) = current.take() {
+        repos.push(repo_entry_from_toml_fields(fields));
+    }
+
+    Ok(repos)
+}
+
+fn repo_entry_from_toml_fields(mut fields: HashMap<String, String>) -> RepoEntry {
+    RepoEntry {
+        group: fields.remove("group").unwrap_or_default(),
+        url: fields.remove("url").unwrap_or_default(),
+        name: fields.remove("name"),
+        branch: fields.remove("branch"),
+        remote: fields.remove("remote"),
+        dir: fields.remove("dir"),
+    }
+}
+
+// status is None when the git process never ran at all (e.g. spawn failure)
+// or ran but exited without a code (ended by a signal)
+#[derive(Debug)]
+struct RepoxError {
+    repo: String,
+    command: String,
+    status: Option<i32>,
+    stderr: String,
+}
+
+impl fmt::Display for RepoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(code) => write!(
+                f,
+                "{} ({}) exited with status {}: {}",
+                self.repo, self.command, code, self.stderr
+            ),
+            None => write!(f, "{} ({}) failed to run or exited without a status: {}", self.repo, self.command, self.stderr),
+        }
+    }
+}
+
+// Builds the `git -C <repo> ...` args for any command not special-cased
+// by process_repo. "exec" is the sentinel used when no COMMAND token
+// precedes `--` (e.g. `repox -- switch -c hotfix`); a real COMMAND token
+// (e.g. `repox push sub -- --tags`) is still the git subcommand and must
+// come before the passthrough args.
+fn other_command_args(local_repo: &Path, cmd: &str, passthrough_args: &[String]) -> Vec<String> {
+    let mut args = vec!["-C".to_string(), local_repo.to_str().unwrap().to_string()];
+    if passthrough_args.is_empty() {
+        args.push(cmd.to_string());
+    } else {
+        if cmd != "exec" {
+            args.push(cmd.to_string());
+        }
+        args.extend(passthrough_args.iter().cloned());
+    }
+    args
+}
+
+fn process_repo(config: &Config, entry: &RepoEntry) -> Result<(), RepoxError> {
+    let started = Instant::now();
+    vlog(config, &entry.url, "dispatch");
+
+    let repo_name = entry.name.clone().unwrap_or_else(|| {
+        entry
+            .url
+            .trim_end_matches(".git")
+            .rsplit('/')
+            .next()
+            .unwrap_or(&entry.url)
+            .to_string()
+    });
+    let local_repo = match &entry.dir {
+        Some(dir) => config.dev_dir.join(dir),
+        None => config.dev_dir.join(&repo_name),
+    };
+    let remote = entry.remote.as_deref().unwrap_or("origin");
 
     let mut cmd = config.cmd.clone();
 
     if !local_repo.exists() && cmd != "clone" {
         cmd = "clone".to_string();
     } else if local_repo.exists() && cmd == "clone" {
-        return;
+        vlog(config, &entry.url, "done (already cloned)");
+        return Ok(());
     }
 
     let output = match cmd.as_str() {
@@ -67,63 +263,108 @@ fn process_repo(config: &Config, repo: &str) {
                 .args(&["-C", local_repo.to_str().unwrap(), "status", "--porcelain"])
                 .output();
 
-            if let Ok(c) = check {
-                if c.stdout.is_empty() {
-                    return;
+            if let Ok(c) = &check {
+                if c.status.success() && c.stdout.is_empty() {
+                    vlog(config, &entry.url, "done (clean)");
+                    return Ok(());
                 }
             }
 
-            Command::new("git")
-                .args(&["-C", local_repo.to_str().unwrap(), "status"])
-                .output()
+            let args = vec!["-C".to_string(), local_repo.to_str().unwrap().to_string(), "status".to_string()];
+            vlog(config, &entry.url, &format!("$ git {}", args.join(" ")));
+
+            Command::new("git").args(&args).output()
         }
 
-        "clone" => Command::new("git")
-            .args(&["-C", config.dev_dir.to_str().unwrap(), "clone", repo])
-            .output(),
+        "clone" => {
+            if let Some(parent) = local_repo.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
 
-        other => Command::new("git")
-            .args(&["-C", local_repo.to_str().unwrap(), other])
-            .output(),
-    };
+            let mut args = vec!["clone".to_string()];
+            if let Some(branch) = &entry.branch {
+                args.push("-b".to_string());
+                args.push(branch.clone());
+            }
+            if remote != "origin" {
+                args.push("--origin".to_string());
+                args.push(remote.to_string());
+            }
+            args.push(entry.url.clone());
+            args.push(local_repo.to_str().unwrap().to_string());
 
-    match output {
-        Ok(out) => {
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            let stderr = String::from_utf8_lossy(&out.stderr);
-
-            print_sync(|| {
-                println!(
-                    "\n\x1B[34m=== {}: {} ===\x1B[0m",
-                    config.cmd.to_uppercase(),
-                    repo
-                );
-                if !stdout.trim().is_empty() {
-                    println!("{}", stdout.trim());
-                }
-                if !stderr.trim().is_empty() {
-                    eprintln!("{}", stderr.trim());
-                }
-            });
+            vlog(config, &entry.url, &format!("$ git {}", args.join(" ")));
+            Command::new("git").args(&args).output()
+        }
+
+        "pull" => {
+            let mut args = vec![
+                "-C".to_string(),
+                local_repo.to_str().unwrap().to_string(),
+                "pull".to_string(),
+                remote.to_string(),
+            ];
+            if let Some(branch) = &entry.branch {
+                args.push(branch.clone());
+            }
+
+            vlog(config, &entry.url, &format!("$ git {}", args.join(" ")));
+            Command::new("git").args(&args).output()
+        }
+
+        other => {
+            let args = other_command_args(&local_repo, other, &config.passthrough_args);
+            vlog(config, &entry.url, &format!("$ git {}", args.join(" ")));
+            Command::new("git").args(&args).output()
         }
+    };
+
+    let out = match output {
+        Ok(out) => out,
         Err(e) => {
-            print_sync(|| {
-                eprintln!(
-                    "\n\x1B[31m[ERROR] {} failed on {}: {}\x1B[0m",
-                    config.cmd, repo, e
-                );
+            log_error(config, &entry.url, &config.cmd, &e);
+            vlog(config, &entry.url, &format!("done (spawn failed, {:.3}s)", started.elapsed().as_secs_f64()));
+            return Err(RepoxError {
+                repo: entry.url.clone(),
+                command: cmd,
+                status: None,
+                stderr: e.to_string(),
             });
         }
+    };
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+
+    log_result(config, &entry.url, &config.cmd, &stdout, &stderr);
+
+    vlog(
+        config,
+        &entry.url,
+        &format!("done ({:.3}s, status {})", started.elapsed().as_secs_f64(), out.status),
+    );
+
+    if !out.status.success() {
+        return Err(RepoxError {
+            repo: entry.url.clone(),
+            command: cmd,
+            status: out.status.code(),
+            stderr: stderr.trim().to_string(),
+        });
     }
+
+    Ok(())
 }
 
-fn run_in_parallel(config: Config, repos: Vec<String>) {
+fn run_in_parallel(config: Config, repos: Vec<RepoEntry>) -> Vec<RepoxError> {
     let shared_repos = Arc::new(Mutex::new(repos));
+    let failures = Arc::new(Mutex::new(Vec::new()));
     let mut handles = vec![];
 
     for _ in 0..config.parallels {
         let cfg = config.clone();
         let repos = Arc::clone(&shared_repos);
+        let failures = Arc::clone(&failures);
 
         let handle = thread::spawn(move || {
             loop {
@@ -136,7 +377,9 @@ fn run_in_parallel(config: Config, repos: Vec<String>) {
                 };
 
                 if let Some(r) = repo {
-                    process_repo(&cfg, &r);
+                    if let Err(e) = process_repo(&cfg, &r) {
+                        failures.lock().unwrap().push(e);
+                    }
                 }
             }
         });
@@ -147,6 +390,11 @@ fn run_in_parallel(config: Config, repos: Vec<String>) {
     for handle in handles {
         let _ = handle.join();
     }
+
+    Arc::try_unwrap(failures)
+        .unwrap_or_else(|_| panic!("all worker threads have been joined"))
+        .into_inner()
+        .unwrap()
 }
 
 fn usage() {
@@ -163,18 +411,41 @@ Commands:
   fetch               Fetch all repos
   pull                Pull all repos
   status              Check status from all repos
+  list [SUBDIRECTORY]  List local clones found under $DEV (cached)
 
 Options:
   -h, --help           Displays this message and exits
   -p <PARALLEL>        Set parallels to use
-  -c <FILE>            Use a specific repox file"
+  -c <FILE>            Use a specific repox file (.toml for structured manifests)
+  -g <GROUP>[,<GROUP>] Only run on repos in these groups
+  --refresh             Force `list` to rescan instead of using the cache
+  -v                    Increase verbosity (repeatable: -vv logs each
+                        repo's dispatch, git command line and duration)
+  -q                    Quiet: print only failures and the final summary
+
+Anything after a `--` separator is run verbatim as a git command across
+all repos, e.g. `repox -- switch -c hotfix`. The status, clone and pull
+commands ignore anything after `--`."
     );
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let raw_args: Vec<String> = env::args().collect();
+    let mut passthrough_args: Vec<String> = Vec::new();
+    let args: Vec<String> = match raw_args.iter().position(|a| a == "--") {
+        Some(sep) => {
+            passthrough_args = raw_args[sep + 1..].to_vec();
+            raw_args[..sep].to_vec()
+        }
+        None => raw_args,
+    };
+
     let mut parallels = 5;
     let mut repox_file: Option<PathBuf> = None;
+    let mut groups: Option<Vec<String>> = None;
+    let mut refresh = false;
+    let mut verbosity: u8 = 0;
+    let mut quiet = false;
 
     let mut i = 1;
     while i < args.len() {
@@ -183,6 +454,12 @@ fn main() {
                 usage();
                 return;
             }
+            "--refresh" => {
+                refresh = true;
+            }
+            "-q" => {
+                quiet = true;
+            }
             "-p" => {
                 i += 1;
                 if i < args.len() {
@@ -195,6 +472,15 @@ fn main() {
                     repox_file = Some(PathBuf::from(&args[i]));
                 }
             }
+            "-g" => {
+                i += 1;
+                if i < args.len() {
+                    groups = Some(args[i].split(',').map(|g| g.trim().to_string()).collect());
+                }
+            }
+            arg if !arg.is_empty() && arg.starts_with('-') && arg[1..].bytes().all(|b| b == b'v') => {
+                verbosity = verbosity.saturating_add((arg.len() - 1) as u8);
+            }
             _ => {
                 break;
             }
@@ -202,18 +488,56 @@ fn main() {
         i += 1;
     }
 
-    if i + 1 > args.len() {
+    if i + 1 > args.len() && passthrough_args.is_empty() {
         eprintln!("ERROR: Missing command");
         exit(1);
-    } else if i + 2 > args.len() {
-        eprintln!("ERROR: Missing subdirectory");
-        exit(1);
     }
 
-    let cmd = args[i].clone();
-    let sub_dir = args[i + 1].clone();
+    // With a `--` passthrough, COMMAND is optional, so a single leftover
+    // positional before `--` is always SUBDIRECTORY, never COMMAND:
+    //   repox CMD SUBDIR -- ARGS -> cmd = CMD,    sub_dir = SUBDIR
+    //   repox SUBDIR -- ARGS     -> cmd = "exec", sub_dir = SUBDIR
+    let (cmd, sub_dir) = if passthrough_args.is_empty() {
+        (args[i].clone(), args.get(i + 1).cloned())
+    } else {
+        match (args.get(i), args.get(i + 1)) {
+            (Some(cmd), Some(sub_dir)) => (cmd.clone(), Some(sub_dir.clone())),
+            (Some(sub_dir), None) => ("exec".to_string(), Some(sub_dir.clone())),
+            (None, _) => ("exec".to_string(), None),
+        }
+    };
 
     let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+
+    if cmd == "list" {
+        let dev_base = match env::var("DEV") {
+            Ok(dev) => PathBuf::from(dev),
+            Err(_) => PathBuf::from(&home).join("dev"),
+        };
+        let scan_root = match &sub_dir {
+            Some(sub_dir) => dev_base.join(sub_dir),
+            None => dev_base,
+        };
+
+        return match cache::list_repos(&scan_root, &home, refresh) {
+            Ok(repos) => {
+                for repo in repos {
+                    println!("{}", repo.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("ERROR: Could not list repos: {}", e);
+                exit(1);
+            }
+        };
+    }
+
+    if sub_dir.is_none() && passthrough_args.is_empty() {
+        eprintln!("ERROR: Missing subdirectory");
+        exit(1);
+    }
+
+    let sub_dir = sub_dir.unwrap_or_default();
     let repox_file = repox_file.unwrap_or_else(|| PathBuf::from(format!("{}/.repox", home)));
 
     if !repox_file.exists() {
@@ -231,7 +555,13 @@ fn main() {
         exit(1);
     }
 
-    let repos = match read_repos(&repox_file) {
+    let is_toml = repox_file.extension().map(|e| e == "toml").unwrap_or(false);
+    let repos = if is_toml {
+        read_repos_toml(&repox_file)
+    } else {
+        read_repos(&repox_file)
+    };
+    let repos = match repos {
         Ok(r) => r,
         Err(e) => {
             eprintln!("ERROR: Could not read repox file: {}", e);
@@ -239,13 +569,138 @@ fn main() {
         }
     };
 
+    let repos: Vec<RepoEntry> = repos
+        .into_iter()
+        .filter(|entry| match &groups {
+            Some(wanted) => wanted.iter().any(|g| g == &entry.group),
+            None => true,
+        })
+        .collect();
+
     let config = Config {
         cmd,
         sub_dir,
         parallels,
         repox_file,
         dev_dir,
+        passthrough_args,
+        verbosity,
+        quiet,
     };
 
-    run_in_parallel(config, repos);
+    let failures = run_in_parallel(config, repos);
+
+    if !failures.is_empty() {
+        eprintln!(
+            "\n\x1B[31m=== Summary: {} repo(s) failed ===\x1B[0m",
+            failures.len()
+        );
+        for failure in &failures {
+            eprintln!("{}", failure);
+        }
+    }
+
+    exit(failures.len().min(125) as i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn temp_file(name: &str) -> PathBuf {
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        env::temp_dir().join(format!("repox-test-{}-{}-{}", std::process::id(), id, name))
+    }
+
+    #[test]
+    fn other_command_args_appends_cmd_then_passthrough() {
+        let args = other_command_args(Path::new("/repo"), "push", &["--tags".to_string()]);
+        assert_eq!(args, vec!["-C", "/repo", "push", "--tags"]);
+    }
+
+    #[test]
+    fn other_command_args_exec_sentinel_omits_cmd() {
+        let passthrough = vec!["switch".to_string(), "-c".to_string(), "hotfix".to_string()];
+        let args = other_command_args(Path::new("/repo"), "exec", &passthrough);
+        assert_eq!(args, vec!["-C", "/repo", "switch", "-c", "hotfix"]);
+    }
+
+    #[test]
+    fn other_command_args_without_passthrough_uses_cmd_only() {
+        let args = other_command_args(Path::new("/repo"), "fetch", &[]);
+        assert_eq!(args, vec!["-C", "/repo", "fetch"]);
+    }
+
+    #[test]
+    fn read_repos_parses_inline_groups() {
+        let path = temp_file("inline.repox");
+        fs::write(&path, "work::git@example.com/acme/api.git\nungrouped-url\n").unwrap();
+
+        let repos = read_repos(&path).unwrap();
+
+        assert_eq!(repos[0].group, "work");
+        assert_eq!(repos[0].url, "git@example.com/acme/api.git");
+        assert_eq!(repos[1].group, "");
+        assert_eq!(repos[1].url, "ungrouped-url");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_repos_parses_section_headers() {
+        let path = temp_file("sections.repox");
+        fs::write(&path, "[work]\nfirst\nsecond\n[personal]\nthird\n").unwrap();
+
+        let repos = read_repos(&path).unwrap();
+        let got: Vec<(&str, &str)> = repos.iter().map(|r| (r.group.as_str(), r.url.as_str())).collect();
+
+        assert_eq!(
+            got,
+            vec![("work", "first"), ("work", "second"), ("personal", "third")]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_repos_toml_parses_overrides() {
+        let path = temp_file("manifest.toml");
+        fs::write(
+            &path,
+            "[[repo]]\nurl = \"git@example.com/acme/api.git\"\nname = \"api\"\nbranch = \"develop\"\n",
+        )
+        .unwrap();
+
+        let repos = read_repos_toml(&path).unwrap();
+
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].url, "git@example.com/acme/api.git");
+        assert_eq!(repos[0].name.as_deref(), Some("api"));
+        assert_eq!(repos[0].branch.as_deref(), Some("develop"));
+        assert_eq!(repos[0].remote, None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_repos_toml_parses_multiple_tables() {
+        let path = temp_file("multi.toml");
+        fs::write(
+            &path,
+            "[[repo]]\nurl = \"a\"\n\n[[repo]]\nurl = \"b\"\ngroup = \"work\"\n",
+        )
+        .unwrap();
+
+        let repos = read_repos_toml(&path).unwrap();
+
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].url, "a");
+        assert_eq!(repos[1].url, "b");
+        assert_eq!(repos[1].group, "work");
+
+        fs::remove_file(&path).unwrap();
+    }
 }